@@ -6,7 +6,7 @@ mod preloader;
 fn main() {
 	#[cfg(target_os = "windows")]
 	{
-		use std::path::PathBuf;
+		use std::{num::NonZeroUsize, path::PathBuf};
 
 		use clap::Parser;
 
@@ -14,16 +14,90 @@ fn main() {
 		#[derive(Debug, Parser)]
 		#[command(author, version, about, long_about)]
 		struct Cli {
-			/// Specifies the directory to preload
+			/// Specifies the directory to preload (prompts interactively if omitted)
 			#[arg(short, long)]
-			dir: PathBuf,
+			dir: Option<PathBuf>,
+
+			/// Recursively descends into subdirectories
+			#[arg(short, long)]
+			recursive: bool,
+
+			/// Limits how deep the recursive descent is permitted to go
+			#[arg(long)]
+			max_depth: Option<usize>,
+
+			/// Specifies the number of worker threads to extract thumbnails with
+			#[arg(short, long, default_value_t = preloader::default_jobs())]
+			jobs: NonZeroUsize,
+
+			/// Runs without the graphical dialog, logging progress to stdout (GUI-less batch operation)
+			#[arg(short, long, visible_alias = "headless")]
+			quiet: bool,
+
+			/// Selects how per-file progress is reported
+			#[arg(long, value_enum, default_value_t = preloader::Format::Text)]
+			format: preloader::Format,
+
+			/// Warms one or more thumbnail sizes, in pixels
+			#[arg(short, long, default_values_t = preloader::default_sizes())]
+			size: Vec<u32>,
+
+			/// Forces fresh extraction even when a cached thumbnail already exists
+			#[arg(short, long)]
+			force: bool,
 		}
 
 		let cli = Cli::parse();
 
-		match preloader::preload(cli.dir) {
-			Ok(()) => println!("Successfully preloaded directory."),
-			Err(err) => println!("Failed to preload directory: {}.", err),
+		let options = preloader::PreloadOptions {
+			recursive: cli.recursive,
+			max_depth: cli.max_depth,
+			jobs: cli.jobs,
+			quiet: cli.quiet,
+			format: cli.format,
+			sizes: cli.size,
+			force: cli.force,
+		};
+
+		// Fall back to the interactive folder picker when no directory is given.
+
+		let dir = match cli.dir {
+			Some(dir) => dir,
+			None => match preloader::pick() {
+				Ok(Some(dir)) => dir,
+				Ok(None) => {
+					if matches!(cli.format, preloader::Format::Json) {
+						eprintln!("No directory selected.");
+					} else {
+						println!("No directory selected.");
+					}
+
+					return;
+				}
+				Err(err) => {
+					if matches!(cli.format, preloader::Format::Json) {
+						eprintln!("Failed to select directory: {}.", err);
+					} else {
+						println!("Failed to select directory: {}.", err);
+					}
+
+					return;
+				}
+			},
+		};
+
+		let message = match preloader::preload(dir, options) {
+			Ok(()) => "Successfully preloaded directory.".to_string(),
+			Err(err) => format!("Failed to preload directory: {}.", err),
+		};
+
+		// In JSON mode stdout must carry only the per-file objects, so the final
+		// summary goes to stderr instead.
+
+		if matches!(cli.format, preloader::Format::Json) {
+			eprintln!("{}", message);
+		} else {
+			println!("{}", message);
 		}
 	}
 
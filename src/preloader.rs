@@ -4,19 +4,29 @@ use std::{
 	error::Error,
 	fmt::{self, Display},
 	fs, io,
+	num::NonZeroUsize,
+	os::windows::fs::MetadataExt,
 	path::{Path, PathBuf},
-	time::Instant,
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
+	thread,
+	time::{Duration, Instant},
+	vec::IntoIter,
 };
 
 use ::windows::Win32::{
-	System::Com::{CoCreateInstance, CoInitialize, CreateBindCtx, CLSCTX_ALL},
-	UI::Shell::{IShellItem, IThumbnailCache, SHCreateItemFromParsingName},
+	Foundation::{E_UNEXPECTED, ERROR_CANCELLED},
+	Storage::FileSystem::{FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_REPARSE_POINT, FILE_ATTRIBUTE_SYSTEM},
+	System::Com::{CoCreateInstance, CoInitialize, CoInitializeEx, CoTaskMemFree, CoUninitialize, CreateBindCtx, CLSCTX_ALL, COINIT_MULTITHREADED},
+	UI::Shell::{Common::SIGDN_FILESYSPATH, CLSID_FileOpenDialog, IFileOpenDialog, IShellItem, IThumbnailCache, SHCreateItemFromParsingName, FOS_PICKFOLDERS},
 };
 use thousands::Separable;
 use windows::Win32::{
 	Foundation::{BOOL, HWND},
 	System::Com::IBindCtx,
-	UI::Shell::{CLSID_ProgressDialog, IProgressDialog, PROGDLG_AUTOTIME, PROGDLG_NOMINIMIZE, WTS_FORCEEXTRACTION},
+	UI::Shell::{CLSID_ProgressDialog, IProgressDialog, PROGDLG_AUTOTIME, PROGDLG_NOMINIMIZE, WTS_EXTRACT, WTS_FLAGS, WTS_FORCEEXTRACTION},
 };
 use windows_core::{w, GUID, HSTRING, PCWSTR};
 
@@ -26,6 +36,69 @@ const LOCAL_THUMBNAIL_CACHE: GUID = GUID::from_u128(0x50ef4544_ac9f_4a8e_b21b_8a
 /// Represents the default dimensions of a thumbnail.
 const DIMENSIONS: u32 = 72;
 
+/// Represents the options that govern how a directory is preloaded.
+#[derive(Debug)]
+pub struct PreloadOptions {
+	/// Indicates whether subdirectories should be descended into.
+	pub recursive: bool,
+
+	/// Limits how deep the recursive descent is permitted to go.
+	pub max_depth: Option<usize>,
+
+	/// Specifies the number of worker threads to extract thumbnails with.
+	pub jobs: NonZeroUsize,
+
+	/// Runs without the graphical progress dialog, logging progress to standard output instead.
+	pub quiet: bool,
+
+	/// Selects how per-file progress is reported.
+	pub format: Format,
+
+	/// The thumbnail sizes to warm, in pixels.
+	pub sizes: Vec<u32>,
+
+	/// Forces fresh extraction even when a cached thumbnail already exists.
+	pub force: bool,
+}
+
+impl Default for PreloadOptions {
+	fn default() -> Self {
+		Self {
+			recursive: false,
+			max_depth: None,
+			jobs: default_jobs(),
+			quiet: false,
+			format: Format::Text,
+			sizes: default_sizes(),
+			force: false,
+		}
+	}
+}
+
+/// Represents the manner in which per-file progress is reported.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum Format {
+	/// Emits human-readable text.
+	#[default]
+	Text,
+
+	/// Emits one machine-readable JSON object per file.
+	Json,
+}
+
+/// Determines the default number of worker threads.
+///
+/// This mirrors the available parallelism of the host, falling back to a single
+/// worker when that cannot be determined.
+pub fn default_jobs() -> NonZeroUsize {
+	thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Returns the thumbnail sizes warmed by default.
+pub fn default_sizes() -> Vec<u32> {
+	vec![DIMENSIONS]
+}
+
 /// Represents a preload-related error.
 #[derive(Debug)]
 pub enum PreloadError {
@@ -41,6 +114,15 @@ pub enum PreloadError {
 	/// Indicates that the progress dialog could not be created.
 	FailedToCreateProgressDialog(windows_core::Error),
 
+	/// Indicates that the folder-selection dialog could not be created.
+	FailedToCreateFileDialog(windows_core::Error),
+
+	/// Indicates that the folder-selection dialog could not be shown.
+	FailedToShowFileDialog(windows_core::Error),
+
+	/// Indicates that the selected folder could not be retrieved.
+	FailedToSelectDirectory(windows_core::Error),
+
 	/// Indicates that the bind context could not be created.
 	FailedToCreateBindContext(windows_core::Error),
 
@@ -56,8 +138,14 @@ pub enum PreloadError {
 	/// Indicates that the progress dialog could not be updated.
 	FailedToUpdateProgressDialog(windows_core::Error),
 
-	/// Indicates that a thumbnail for a particular file could not be generated.
-	FailedToGenerateThumbnail(windows_core::Error),
+	/// Indicates that a thumbnail of a particular size for a particular file could not be generated.
+	FailedToGenerateThumbnail {
+		/// The thumbnail size that was being requested.
+		size: u32,
+
+		/// The underlying shell error.
+		source: windows_core::Error,
+	},
 
 	/// Indicates that the progress dialog could not be hidden.
 	FailedToHideProgressDialog(windows_core::Error),
@@ -73,12 +161,15 @@ impl Display for PreloadError {
 			Self::FailedToReadDirectory(e) => write!(f, "failed to read directory [{}]", e),
 			Self::FailedToInitialiseCOM(e) => write!(f, "failed to initialise COM [{}]", e),
 			Self::FailedToCreateProgressDialog(e) => write!(f, "failed to create progress dialog [{}]", e),
+			Self::FailedToCreateFileDialog(e) => write!(f, "failed to create folder-selection dialog [{}]", e),
+			Self::FailedToShowFileDialog(e) => write!(f, "failed to show folder-selection dialog [{}]", e),
+			Self::FailedToSelectDirectory(e) => write!(f, "failed to select directory [{}]", e),
 			Self::FailedToCreateThumbnailCache(e) => write!(f, "failed to create thumbnail cache [{}]", e),
 			Self::FailedToCreateBindContext(e) => write!(f, "failed to create bind context [{}]", e),
 			Self::FailedToShowProgressDialog(e) => write!(f, "failed to show progress dialog [{}]", e),
 			Self::FailedToUpdateProgressDialog(e) => write!(f, "failed to update progress dialog [{}]", e),
 			Self::FailedToCreateShellItem(e) => write!(f, "failed to create shell item [{}]", e),
-			Self::FailedToGenerateThumbnail(e) => write!(f, "failed to generate thumbnail [{}]", e),
+			Self::FailedToGenerateThumbnail { size, source } => write!(f, "failed to generate {}px thumbnail [{}]", size, source),
 			Self::FailedToHideProgressDialog(e) => write!(f, "failed to hide progress dialog [{}]", e),
 		}
 	}
@@ -86,122 +177,405 @@ impl Display for PreloadError {
 
 impl Error for PreloadError {}
 
+/// Reports per-file progress throughout a preload operation.
+///
+/// Implementations are shared across the worker pool and so must be safe to
+/// use from multiple threads concurrently.
+pub trait Reporter: Send + Sync {
+	/// Called immediately before a file's thumbnail is extracted.
+	fn file_started(&self, path: &Path);
+
+	/// Called once a file's thumbnail extraction has finished, whether or not it succeeded.
+	fn file_finished(&self, index: usize, total: usize, path: &Path, result: &Result<(), PreloadError>);
+}
+
+/// Reports progress as human-readable text on standard output.
+pub struct ConsoleReporter;
+
+impl Reporter for ConsoleReporter {
+	fn file_started(&self, _path: &Path) {}
+
+	fn file_finished(&self, index: usize, total: usize, path: &Path, result: &Result<(), PreloadError>) {
+		match result {
+			Ok(()) => println!("Preloaded file {} of {}: <{}>.", index, total, path.display()),
+			Err(err) => println!("Failed to preload file {} of {}: <{}>: {}.", index, total, path.display(), err),
+		}
+	}
+}
+
+/// Reports nothing per file, for use while the graphical progress dialog is shown.
+///
+/// The dialog itself is updated from the main thread's shared counter, not by
+/// this reporter, and nothing is emitted on standard output.
+pub struct DialogReporter;
+
+impl Reporter for DialogReporter {
+	fn file_started(&self, _path: &Path) {}
+
+	fn file_finished(&self, _index: usize, _total: usize, _path: &Path, _result: &Result<(), PreloadError>) {}
+}
+
+/// Reports progress as one JSON object per file on standard output.
+pub struct JsonReporter;
+
+impl Reporter for JsonReporter {
+	fn file_started(&self, _path: &Path) {}
+
+	fn file_finished(&self, index: usize, total: usize, path: &Path, result: &Result<(), PreloadError>) {
+		let (status, error) = match result {
+			Ok(()) => ("ok", String::new()),
+			Err(err) => ("failed", err.to_string()),
+		};
+
+		println!(
+			"{{\"index\":{},\"total\":{},\"path\":{},\"status\":\"{}\",\"error\":{}}}",
+			index,
+			total,
+			json_string(&path.display().to_string()),
+			status,
+			json_string(&error)
+		);
+	}
+}
+
+/// Escapes and quotes an arbitrary string as a JSON string literal.
+///
+/// Control characters are always escaped, using the short forms (`\n`, `\r`,
+/// `\t`, `\b`, `\f`) where JSON has one and the `\uXXXX` form otherwise, so
+/// values containing shell output or unusual filenames remain valid JSON
+/// without pulling in a serialisation dependency.
+fn json_string(value: &str) -> String {
+	let mut escaped = String::with_capacity(value.len() + 2);
+
+	escaped.push('"');
+
+	for c in value.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			'\u{08}' => escaped.push_str("\\b"),
+			'\u{0c}' => escaped.push_str("\\f"),
+			c if (c as u32) < 0x20 || c as u32 == 0x7f => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+
+	escaped.push('"');
+
+	escaped
+}
+
+/// Prompts the user to interactively select a directory to preload.
+///
+/// The Windows common folder-selection dialog is shown; a successful selection
+/// yields its file-system path, whereas a user cancellation yields `None` so
+/// the caller can exit cleanly.
+pub fn pick() -> Result<Option<PathBuf>, PreloadError> {
+	// Initialise COM so the dialog can be created.
+
+	unsafe { CoInitialize(None) }.map_err(PreloadError::FailedToInitialiseCOM)?;
+
+	let dialog: IFileOpenDialog = unsafe { CoCreateInstance(&CLSID_FileOpenDialog, None, CLSCTX_ALL) }.map_err(PreloadError::FailedToCreateFileDialog)?;
+
+	// Restrict the dialog to picking folders rather than files.
+
+	unsafe {
+		let options = dialog.GetOptions().map_err(PreloadError::FailedToCreateFileDialog)?;
+
+		dialog.SetOptions(options | FOS_PICKFOLDERS).map_err(PreloadError::FailedToCreateFileDialog)?;
+	}
+
+	// Show the dialog, treating a user cancellation as a clean exit.
+
+	match unsafe { dialog.Show(None) } {
+		Ok(()) => {}
+		Err(err) if err.code() == ERROR_CANCELLED.to_hresult() => return Ok(None),
+		Err(err) => return Err(PreloadError::FailedToShowFileDialog(err)),
+	}
+
+	let item: IShellItem = unsafe { dialog.GetResult() }.map_err(PreloadError::FailedToSelectDirectory)?;
+	let display = unsafe { item.GetDisplayName(SIGDN_FILESYSPATH) }.map_err(PreloadError::FailedToSelectDirectory)?;
+
+	// Copy the path out before freeing the buffer allocated by the shell.
+
+	let path = unsafe { display.to_string() }.map_err(|_| PreloadError::FailedToSelectDirectory(windows_core::Error::from(E_UNEXPECTED)))?;
+
+	unsafe { CoTaskMemFree(Some(display.as_ptr() as _)) };
+
+	Ok(Some(PathBuf::from(path)))
+}
+
 /// Attempts to preload the specified directory.
-pub fn preload<T>(dir: T) -> PreloadResult
+pub fn preload<T>(dir: T, options: PreloadOptions) -> PreloadResult
 where
 	T: AsRef<Path>,
 {
-	println!("Preloading thumbnails for files in directory <{}>...", dir.as_ref().display());
+	// Informational chatter is only for interactive text runs. JSON mode keeps
+	// stdout free for structured lines, and quiet mode drops the chatter too,
+	// leaving the per-file reporter output and any worker failures.
+
+	let chatty = matches!(options.format, Format::Text) && !options.quiet;
+	let json = matches!(options.format, Format::Json);
+
+	if chatty {
+		println!("Preloading thumbnails for files in directory <{}>...", dir.as_ref().display());
+	}
 
 	let dir = dir.as_ref().canonicalize().map_err(PreloadError::InvalidDirectory)?;
 
 	let start = Instant::now();
 
-	println!("Searching for files...");
+	if chatty {
+		println!("Searching for files...");
+	}
 
 	// Search for files and then convert them to a classic-style Windows path.
-	// UNC paths do not play nice.
+	// UNC paths do not play nice. Enumeration is finished up-front so that the
+	// progress dialog's total is accurate before it is shown.
 
-	let files: Vec<PathBuf> = fs::read_dir(dir)
-		.map_err(PreloadError::FailedToReadDirectory)?
-		.flatten()
-		.map(|d| dunce::canonicalize(d.path()))
-		.flatten()
-		.collect();
+	let mut files: Vec<PathBuf> = Vec::new();
 
-	println!("Searched for {} files in {:#?}.", files.len(), start.elapsed());
-	println!("Initialising COM...");
+	collect(&dir, options.recursive, options.max_depth, 0, &mut files)?;
 
-	// Initialise COM.
+	if chatty {
+		println!("Searched for {} files in {:#?}.", files.len(), start.elapsed());
+		println!("Initialising COM...");
+	}
+
+	// Initialise COM on the main thread for the progress dialog.
+	// Each worker initialises its own multithreaded apartment separately.
 
 	unsafe { CoInitialize(None) }.map_err(PreloadError::FailedToInitialiseCOM)?;
 
-	println!("Creating thumbnail cache...");
+	// Select the reporter and decide whether to show the graphical dialog.
+	// The dialog is only meaningful in interactive text mode.
 
-	// Initialise the IThumbnailCache instance.
+	let show_dialog = !options.quiet && matches!(options.format, Format::Text);
 
-	let bind_ctx = unsafe { CreateBindCtx(0) }.map_err(PreloadError::FailedToCreateBindContext)?;
-	let thumb_cache: IThumbnailCache = unsafe { CoCreateInstance(&LOCAL_THUMBNAIL_CACHE, None, CLSCTX_ALL) }.map_err(PreloadError::FailedToCreateThumbnailCache)?;
+	let reporter: Arc<dyn Reporter> = match options.format {
+		Format::Json => Arc::new(JsonReporter),
+		Format::Text if show_dialog => Arc::new(DialogReporter),
+		Format::Text => Arc::new(ConsoleReporter),
+	};
 
-	println!("Creating progress dialog...");
+	// Set up and show the progress dialog, unless running headless.
+	// We make use of the automatic time feature to provide an estimate on completion.
 
-	// Set up the progress dialog.
+	let progress_dialog: Option<IProgressDialog> = if show_dialog {
+		println!("Creating progress dialog...");
 
-	let progress_dialog: IProgressDialog = unsafe { CoCreateInstance(&CLSID_ProgressDialog, None, CLSCTX_ALL) }.map_err(PreloadError::FailedToCreateProgressDialog)?;
+		let dialog: IProgressDialog = unsafe { CoCreateInstance(&CLSID_ProgressDialog, None, CLSCTX_ALL) }.map_err(PreloadError::FailedToCreateProgressDialog)?;
 
-	unsafe {
-		progress_dialog
-			.SetTitle(w!("Windows Thumbnail Preloader"))
-			.map_err(PreloadError::FailedToCreateProgressDialog)?;
+		unsafe {
+			dialog
+				.SetTitle(w!("Windows Thumbnail Preloader"))
+				.map_err(PreloadError::FailedToCreateProgressDialog)?;
 
-		progress_dialog
-			.SetLine(1, PCWSTR(HSTRING::from(format!("Preloading {} files", files.len().separate_with_commas())).as_ptr()), BOOL(1), None)
-			.map_err(PreloadError::FailedToCreateProgressDialog)?;
-	}
+			dialog
+				.SetLine(1, PCWSTR(HSTRING::from(format!("Preloading {} files", files.len().separate_with_commas())).as_ptr()), BOOL(1), None)
+				.map_err(PreloadError::FailedToCreateProgressDialog)?;
+
+			dialog
+				.StartProgressDialog(HWND(0), None, PROGDLG_AUTOTIME | PROGDLG_NOMINIMIZE, None)
+				.map_err(PreloadError::FailedToShowProgressDialog)?;
+		}
+
+		Some(dialog)
+	} else {
+		None
+	};
 
 	let start = Instant::now();
 
-	// Show the progress dialog.
-	// We make use of the automatic time feature to provide an estimate on completion.
+	let count = files.len();
+	let total: u32 = count.try_into().expect("failed to convert progress total");
 
-	unsafe { progress_dialog.StartProgressDialog(HWND(0), None, PROGDLG_AUTOTIME | PROGDLG_NOMINIMIZE, None) }.map_err(PreloadError::FailedToShowProgressDialog)?;
+	if chatty {
+		println!("Preloading {} files across {} workers...", count, options.jobs);
+	}
 
-	println!("Preloading {} files...", files.len());
+	// Hand the files off to a shared work queue that the workers drain.
+	// COM interfaces are not safe to share across apartments, so each worker
+	// creates its own and pulls only plain paths off the queue.
 
-	for (index, path) in files.iter().enumerate() {
-		unsafe {
-			if progress_dialog.HasUserCancelled().into() {
-				break;
+	let queue: Arc<Mutex<IntoIter<PathBuf>>> = Arc::new(Mutex::new(files.into_iter()));
+	let progress = Arc::new(AtomicUsize::new(0));
+	let cancelled = Arc::new(AtomicBool::new(false));
+
+	// Default to reusing any cached entry, only forcing fresh extraction on request.
+
+	let sizes: Arc<[u32]> = Arc::from(options.sizes);
+	let flags = if options.force { WTS_FORCEEXTRACTION } else { WTS_EXTRACT };
+
+	let workers: Vec<_> = (0..options.jobs.get())
+		.map(|_| {
+			let queue = Arc::clone(&queue);
+			let progress = Arc::clone(&progress);
+			let cancelled = Arc::clone(&cancelled);
+			let reporter = Arc::clone(&reporter);
+			let sizes = Arc::clone(&sizes);
+
+			thread::spawn(move || work(&queue, &progress, &cancelled, count, &sizes, flags, reporter.as_ref()))
+		})
+		.collect();
+
+	// Drive the progress dialog from the main thread, polling for cancellation
+	// and pushing the shared counter into the dialog on a timer rather than
+	// once per file. Without a dialog we simply wait for the queue to drain.
+
+	loop {
+		let done = progress.load(Ordering::Relaxed);
+
+		if let Some(dialog) = &progress_dialog {
+			unsafe {
+				if dialog.HasUserCancelled().into() {
+					cancelled.store(true, Ordering::Relaxed);
+
+					break;
+				}
+
+				dialog
+					.SetProgress(done.try_into().unwrap_or(total), total)
+					.map_err(PreloadError::FailedToUpdateProgressDialog)?;
 			}
 		}
 
-		println!("Preloading file {} of {}: <{}>...", index + 1, files.len(), path.display());
+		if done >= count {
+			break;
+		}
+
+		thread::sleep(Duration::from_millis(100));
+	}
 
-		let current: u32 = index.try_into().expect("failed to convert progress index");
-		let total: u32 = files.len().try_into().expect("failed to convert progress total");
+	// Wait for the workers to drain the queue (or notice the cancellation).
 
-		// Update the progress dialog with the current progress information.
+	for worker in workers {
+		if let Err(err) = worker.join().expect("worker thread panicked") {
+			// Keep stdout clean for machine consumers; report setup failures on stderr instead.
 
-		unsafe {
-			progress_dialog
-				.SetProgress(current, total)
-				.map_err(PreloadError::FailedToUpdateProgressDialog)?;
+			if json {
+				eprintln!("Worker failed: {}.", err);
+			} else {
+				println!("Worker failed: {}.", err);
+			}
 		}
+	}
 
-		unsafe {
-			let line = PCWSTR(HSTRING::from(path.as_os_str()).as_ptr());
+	if chatty {
+		println!("Preloaded files in {:#?}.", start.elapsed());
+	}
 
-			progress_dialog
-				.SetLine(2, line, BOOL(1), None)
-				.map_err(PreloadError::FailedToUpdateProgressDialog)?;
-		}
+	if let Some(dialog) = &progress_dialog {
+		unsafe { dialog.StopProgressDialog() }.map_err(PreloadError::FailedToHideProgressDialog)?;
+	}
+
+	Ok(())
+}
 
-		// Attempt to generate the individual thumbnail.
+/// Recursively collects the files beneath the specified directory.
+///
+/// When `recursive` is not set only the top-level directory is considered.
+/// Otherwise subdirectories are descended into depth-first, stopping at the
+/// optional maximum depth and skipping hidden or system directories so that
+/// very large trees do not explode the file count. Reparse points (directory
+/// symlinks and junctions) are never followed, which keeps a link back to an
+/// ancestor from recursing until the stack overflows.
+fn collect(dir: &Path, recursive: bool, max_depth: Option<usize>, depth: usize, files: &mut Vec<PathBuf>) -> Result<(), PreloadError> {
+	let entries = fs::read_dir(dir).map_err(PreloadError::FailedToReadDirectory)?;
+
+	for entry in entries.flatten() {
+		let Ok(metadata) = entry.metadata() else {
+			continue;
+		};
+
+		if metadata.is_dir() {
+			if !recursive || is_hidden_or_system(&metadata) || is_reparse_point(&metadata) || max_depth.is_some_and(|max| depth >= max) {
+				continue;
+			}
 
-		if let Err(err) = generate(&bind_ctx, &thumb_cache, path) {
-			println!("Failed to preload file: {}.", err);
+			collect(&entry.path(), recursive, max_depth, depth + 1, files)?;
+		} else if let Ok(path) = dunce::canonicalize(entry.path()) {
+			files.push(path);
 		}
 	}
 
-	println!("Preloaded files in {:#?}.", start.elapsed());
+	Ok(())
+}
+
+/// Determines whether the specified metadata describes a hidden or system entry.
+fn is_hidden_or_system(metadata: &fs::Metadata) -> bool {
+	metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN.0 | FILE_ATTRIBUTE_SYSTEM.0) != 0
+}
+
+/// Determines whether the specified metadata describes a reparse point, such as a symlink or junction.
+fn is_reparse_point(metadata: &fs::Metadata) -> bool {
+	metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT.0 != 0
+}
+
+/// Runs a single worker, extracting thumbnails until the queue is drained.
+///
+/// Each worker lives in its own multithreaded apartment with its own bind
+/// context and thumbnail cache, as these COM objects cannot be shared across
+/// apartments. Per-file failures are reported but do not abort the pool.
+fn work(queue: &Mutex<IntoIter<PathBuf>>, progress: &AtomicUsize, cancelled: &AtomicBool, total: usize, sizes: &[u32], flags: WTS_FLAGS, reporter: &dyn Reporter) -> Result<(), PreloadError> {
+	unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.ok().map_err(PreloadError::FailedToInitialiseCOM)?;
 
-	unsafe { progress_dialog.StopProgressDialog() }.map_err(PreloadError::FailedToHideProgressDialog)?;
+	// Perform the extraction within an inner scope so that the COM objects are
+	// dropped before the apartment is torn down.
+
+	let result = extract(queue, progress, cancelled, total, sizes, flags, reporter);
+
+	unsafe { CoUninitialize() };
+
+	result
+}
+
+/// Drains the shared work queue, extracting a thumbnail for each path.
+fn extract(queue: &Mutex<IntoIter<PathBuf>>, progress: &AtomicUsize, cancelled: &AtomicBool, total: usize, sizes: &[u32], flags: WTS_FLAGS, reporter: &dyn Reporter) -> Result<(), PreloadError> {
+	let bind_ctx = unsafe { CreateBindCtx(0) }.map_err(PreloadError::FailedToCreateBindContext)?;
+	let thumb_cache: IThumbnailCache = unsafe { CoCreateInstance(&LOCAL_THUMBNAIL_CACHE, None, CLSCTX_ALL) }.map_err(PreloadError::FailedToCreateThumbnailCache)?;
+
+	loop {
+		if cancelled.load(Ordering::Relaxed) {
+			break;
+		}
+
+		// Pull a single path off the queue, releasing the lock before the
+		// expensive extraction so the other workers can make progress.
+
+		let Some(path) = queue.lock().expect("work queue mutex poisoned").next() else {
+			break;
+		};
+
+		reporter.file_started(&path);
+
+		let result = generate(&bind_ctx, &thumb_cache, &path, sizes, flags);
+		let index = progress.fetch_add(1, Ordering::Relaxed) + 1;
+
+		reporter.file_finished(index, total, &path, &result);
+	}
 
 	Ok(())
 }
 
 /// Attempts to retrieve a thumbnail for the specified path from the specified thumbnail cache.
-fn generate<T>(bind_ctx: &IBindCtx, thumb_cache: &IThumbnailCache, path: T) -> Result<(), PreloadError>
+fn generate<T>(bind_ctx: &IBindCtx, thumb_cache: &IThumbnailCache, path: T, sizes: &[u32], flags: WTS_FLAGS) -> Result<(), PreloadError>
 where
 	T: AsRef<Path>,
 {
 	let pszpath = PCWSTR(HSTRING::from(path.as_ref()).as_ptr());
 	let shell_item: IShellItem = unsafe { SHCreateItemFromParsingName(pszpath, bind_ctx) }.map_err(PreloadError::FailedToCreateShellItem)?;
 
-	// Attempt to retrieve the thumbnail from the thumbnail cache.
-	// This causes the thumbnail to actually be generated, even if one already exists.
+	// Warm every requested size in a single pass so that each cache tier that
+	// Explorer serves in its various view modes gets populated. Forcing fresh
+	// extraction is optional, so repeat runs over an already-warm folder stay cheap.
 
-	unsafe { thumb_cache.GetThumbnail(&shell_item, DIMENSIONS, WTS_FORCEEXTRACTION, None, None, None) }.map_err(PreloadError::FailedToGenerateThumbnail)?;
+	for &size in sizes {
+		unsafe { thumb_cache.GetThumbnail(&shell_item, size, flags, None, None, None) }.map_err(|source| PreloadError::FailedToGenerateThumbnail { size, source })?;
+	}
 
 	Ok(())
 }